@@ -1,6 +1,9 @@
+use std::convert::TryInto;
 use std::sync::mpsc;
 use std::thread;
 
+use crate::device::Error;
+
 pub struct Console {
     pub previous_lines: Vec<String>,
     current_line: String,
@@ -28,20 +31,17 @@ impl Console {
         if let Some(rec) = &self.receiver {
             match rec.try_recv() {
                 Ok(m) => {
-                    match m {
-                        Packet::Chars(t) => {
-                            // println!("{:?}", &t);
-                            for c in &t {
-                                if self.current_line.len() < self.text_width as usize && *c != '\n'{
-                                    self.cursor_position += 1;
-                                    self.current_line.push(*c);
-                                } else {
-                                    self.newline();
-                                }
+                    if let Packet::Chars(t) = m {
+                        // println!("{:?}", &t);
+                        for c in &t {
+                            if self.current_line.len() < self.text_width as usize && *c != '\n'{
+                                self.cursor_position += 1;
+                                self.current_line.push(*c);
+                            } else {
+                                self.newline();
                             }
-                            // println!("{}", &self.current_line);
-                        },
-                        Packet::End => {}
+                        }
+                        // println!("{}", &self.current_line);
                     }
                 },
                 Err(_) => ()
@@ -72,5 +72,101 @@ impl Console {
 #[derive(Copy, Clone, Debug)]
 pub enum Packet {
     Chars([char; 8]),
+    /// Asks whatever `AddressSpace` the packet is routed to for `len` bytes
+    /// starting at `addr`.
+    MemoryRead { addr: u32, len: u8 },
+    /// Asks whatever `AddressSpace` the packet is routed to to write `data`
+    /// starting at `addr`.
+    MemoryWrite { addr: u32, data: [u8; 8] },
+    /// A `MemoryRead`/`MemoryWrite` response carrying the bytes read from,
+    /// or just written to, `addr`.
+    MemoryData { addr: u32, data: [u8; 8] },
     End
+}
+
+const TAG_CHARS: u8 = 0;
+const TAG_END: u8 = 1;
+const TAG_MEMORY_READ: u8 = 2;
+const TAG_MEMORY_WRITE: u8 = 3;
+const TAG_MEMORY_DATA: u8 = 4;
+
+impl Packet {
+
+    ///
+    /// Serializes a `Packet` to bytes for transport over a link: `Chars`
+    /// becomes its 8 characters UTF-8-padded to 4 bytes apiece (32 bytes
+    /// total), the memory variants become their fields in field-declaration
+    /// order, and `End` is empty -- all followed by a 1-byte tag.
+    ///
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Packet::Chars(chars) => {
+                let mut bytes = Vec::with_capacity(33);
+                for c in chars {
+                    let mut padded = [0u8; 4];
+                    let mut encode_buf = [0u8; 4];
+                    let encoded = c.encode_utf8(&mut encode_buf).as_bytes();
+                    padded[..encoded.len()].copy_from_slice(encoded);
+                    bytes.extend_from_slice(&padded);
+                }
+                bytes.push(TAG_CHARS);
+                bytes
+            },
+            Packet::MemoryRead { addr, len } => {
+                let mut bytes = Vec::with_capacity(6);
+                bytes.extend_from_slice(&addr.to_le_bytes());
+                bytes.push(*len);
+                bytes.push(TAG_MEMORY_READ);
+                bytes
+            },
+            Packet::MemoryWrite { addr, data } => {
+                let mut bytes = Vec::with_capacity(13);
+                bytes.extend_from_slice(&addr.to_le_bytes());
+                bytes.extend_from_slice(data);
+                bytes.push(TAG_MEMORY_WRITE);
+                bytes
+            },
+            Packet::MemoryData { addr, data } => {
+                let mut bytes = Vec::with_capacity(13);
+                bytes.extend_from_slice(&addr.to_le_bytes());
+                bytes.extend_from_slice(data);
+                bytes.push(TAG_MEMORY_DATA);
+                bytes
+            },
+            Packet::End => vec![TAG_END]
+        }
+    }
+
+    ///
+    /// The inverse of `to_bytes`.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> Result<Packet, Error> {
+        match bytes.last() {
+            Some(&TAG_END) if bytes.len() == 1 => Ok(Packet::End),
+            Some(&TAG_CHARS) if bytes.len() == 33 => {
+                let mut chars = ['\0'; 8];
+                for (i, chunk) in bytes[..32].chunks(4).enumerate() {
+                    let len = chunk.iter().position(|&b| b == 0).unwrap_or(4).max(1);
+                    let decoded = std::str::from_utf8(&chunk[..len]).map_err(|_| Error::InvalidPacket)?;
+                    chars[i] = decoded.chars().next().ok_or(Error::InvalidPacket)?;
+                }
+                Ok(Packet::Chars(chars))
+            },
+            Some(&TAG_MEMORY_READ) if bytes.len() == 6 => {
+                let addr = u32::from_le_bytes(bytes[0..4].try_into().map_err(|_| Error::InvalidPacket)?);
+                Ok(Packet::MemoryRead { addr, len: bytes[4] })
+            },
+            Some(&TAG_MEMORY_WRITE) if bytes.len() == 13 => {
+                let addr = u32::from_le_bytes(bytes[0..4].try_into().map_err(|_| Error::InvalidPacket)?);
+                let data: [u8; 8] = bytes[4..12].try_into().map_err(|_| Error::InvalidPacket)?;
+                Ok(Packet::MemoryWrite { addr, data })
+            },
+            Some(&TAG_MEMORY_DATA) if bytes.len() == 13 => {
+                let addr = u32::from_le_bytes(bytes[0..4].try_into().map_err(|_| Error::InvalidPacket)?);
+                let data: [u8; 8] = bytes[4..12].try_into().map_err(|_| Error::InvalidPacket)?;
+                Ok(Packet::MemoryData { addr, data })
+            },
+            _ => Err(Error::InvalidPacket)
+        }
+    }
 }
\ No newline at end of file
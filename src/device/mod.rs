@@ -1,14 +1,169 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
 use std::sync;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::system::Address;
 use super::Packet;
-use std::fmt;
 
-struct Process {
-    input: sync::mpsc::Receiver<Packet>,
-    output: sync::mpsc::Sender<Packet>
+#[derive(Debug)]
+pub enum Error {
+    OutOfBounds,
+    ReadOnly,
+    InvalidPacket,
+    Io(io::Error)
 }
 
-trait Run {
-    fn run();
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+pub struct Process {
+    pub input: sync::mpsc::Receiver<Packet>,
+    pub output: sync::mpsc::Sender<Packet>
+}
+
+///
+/// Implemented by whatever packet-handling behavior a device kind needs.
+/// `run` owns everything it needs (its `Process`, any device state) up
+/// front, and is handed the scheduler's shared "deleting" flag so it can
+/// cooperate with a coordinated shutdown instead of blocking forever. Takes
+/// `self` boxed so a `Scheduler` can host a different `Run` per device kind
+/// behind one trait object instead of being monomorphic over a single type.
+///
+pub trait Run {
+    fn run(self: Box<Self>, stop: Arc<AtomicBool>);
+}
+
+///
+/// A `Run` that owns a `Process` and feeds every packet it `recv`s through
+/// `transform` before handing the result to its output channel. Exits as
+/// soon as it sees a `Packet::End`, its input channel disconnects, or its
+/// output channel has no more receivers.
+///
+pub struct ProcessRunner<F: FnMut(Packet) -> Packet> {
+    pub process: Process,
+    pub transform: F
+}
+
+impl<F: FnMut(Packet) -> Packet + Send> Run for ProcessRunner<F> {
+    fn run(self: Box<Self>, stop: Arc<AtomicBool>) {
+        let ProcessRunner { process, mut transform } = *self;
+
+        loop {
+            if stop.load(Ordering::Acquire) {
+                break;
+            }
+
+            match process.input.recv() {
+                Ok(Packet::End) => break,
+                Ok(pkt) => {
+                    let out = transform(pkt);
+                    if process.output.send(out).is_err() {
+                        break;
+                    }
+                },
+                Err(_) => break
+            }
+        }
+    }
+}
+
+///
+/// Coordinates a fleet of `Run` threads, one per device `Address`. Runners
+/// are boxed trait objects, so a single scheduler can host a `Mainframe`'s
+/// memory-handling behavior alongside a `Terminal`'s link-framing behavior
+/// side by side. A shared "deleting" flag is checked with `Acquire`
+/// ordering at the top of each runner's loop, and a `Condvar` lets
+/// `shutdown` block until every runner has actually finished instead of
+/// just assuming the `Packet::End` it sent was enough.
+///
+pub struct Scheduler {
+    runners: HashMap<Address, Box<dyn Run + Send>>,
+    inputs: HashMap<Address, sync::mpsc::Sender<Packet>>,
+    deleting: Arc<AtomicBool>,
+    active: Arc<(Mutex<usize>, Condvar)>,
+    handles: Vec<JoinHandle<()>>
+}
+
+impl Scheduler {
+    pub fn new() -> Scheduler {
+        Scheduler {
+            runners: HashMap::new(),
+            inputs: HashMap::new(),
+            deleting: Arc::new(AtomicBool::new(false)),
+            active: Arc::new((Mutex::new(0), Condvar::new())),
+            handles: Vec::new()
+        }
+    }
+
+    ///
+    /// Registers a device's runner under `address`, along with the sender
+    /// half of its input channel so `shutdown` can push it a `Packet::End`
+    /// directly to wake a blocked `recv`.
+    ///
+    pub fn register(&mut self, address: Address, runner: Box<dyn Run + Send>, input: sync::mpsc::Sender<Packet>) {
+        self.runners.insert(address, runner);
+        self.inputs.insert(address, input);
+    }
+
+    ///
+    /// Spawns one thread per registered runner, handing each a clone of the
+    /// shared "deleting" flag.
+    ///
+    pub fn spawn_all(&mut self) {
+        for (_, runner) in self.runners.drain() {
+            let stop = self.deleting.clone();
+            let active = self.active.clone();
+
+            {
+                let (count, _) = &*active;
+                *count.lock().unwrap() += 1;
+            }
+
+            let handle = thread::spawn(move || {
+                runner.run(stop);
+
+                let (count, condvar) = &*active;
+                *count.lock().unwrap() -= 1;
+                condvar.notify_all();
+            });
+            self.handles.push(handle);
+        }
+    }
+
+    ///
+    /// Sets the "deleting" flag, broadcasts a `Packet::End` to every
+    /// registered input so any runner blocked on `recv` wakes up, waits for
+    /// every runner to drain, then joins every thread.
+    ///
+    pub fn shutdown(&mut self) {
+        self.deleting.store(true, Ordering::Release);
+
+        for (_, input) in self.inputs.drain() {
+            let _ = input.send(Packet::End);
+        }
+
+        let (count, condvar) = &*self.active;
+        let drained = condvar.wait_while(count.lock().unwrap(), |remaining| *remaining > 0).unwrap();
+        drop(drained);
+
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Scheduler {
+        Scheduler::new()
+    }
 }
 
 pub struct Device {
@@ -17,6 +172,105 @@ pub struct Device {
     parent_device: Option<Box<Device>>
 }
 
+///
+/// Implemented by anything that exposes a flat, byte-addressed memory space
+/// a device can be read from or written to.
+///
+pub trait AddressSpace {
+    fn size(&self) -> usize;
+    fn read(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Error>;
+    fn write(&mut self, addr: u32, data: &[u8]) -> Result<(), Error>;
+}
+
+///
+/// A plain, in-memory backing store for an `AddressSpace` device. Can be
+/// seeded from disk wholesale with `load`, or patched at an offset with
+/// `load_at`.
+///
+pub struct MemoryBlock {
+    read_only: bool,
+    contents: Vec<u8>
+}
+
+impl MemoryBlock {
+    pub fn new(size: usize) -> MemoryBlock {
+        MemoryBlock {
+            read_only: false,
+            contents: vec![0; size]
+        }
+    }
+
+    ///
+    /// Reads the entire contents of `filename` into a new `MemoryBlock`
+    /// sized to fit.
+    ///
+    pub fn load(filename: &str) -> Result<MemoryBlock, Error> {
+        let contents = fs::read(filename)?;
+        Ok(MemoryBlock {
+            read_only: false,
+            contents
+        })
+    }
+
+    ///
+    /// Reads `filename` and writes its contents into `self` starting at
+    /// `addr`, growing the block first if the file doesn't fit.
+    ///
+    pub fn load_at(&mut self, addr: u32, filename: &str) -> Result<(), Error> {
+        let data = fs::read(filename)?;
+        let needed = addr as usize + data.len();
+        if needed > self.contents.len() {
+            self.resize(needed);
+        }
+        self.write(addr, &data)
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    ///
+    /// Marks the block read-only; every `write` after this returns
+    /// `Error::ReadOnly` instead of touching `contents`.
+    ///
+    pub fn lock(&mut self) {
+        self.read_only = true;
+    }
+
+    pub fn resize(&mut self, size: usize) {
+        self.contents.resize(size, 0);
+    }
+}
+
+impl AddressSpace for MemoryBlock {
+    fn size(&self) -> usize {
+        self.contents.len()
+    }
+
+    fn read(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Error> {
+        let start = addr as usize;
+        let end = start + data.len();
+        if end > self.contents.len() {
+            return Err(Error::OutOfBounds);
+        }
+        data.copy_from_slice(&self.contents[start..end]);
+        Ok(())
+    }
+
+    fn write(&mut self, addr: u32, data: &[u8]) -> Result<(), Error> {
+        if self.read_only {
+            return Err(Error::ReadOnly);
+        }
+        let start = addr as usize;
+        let end = start + data.len();
+        if end > self.contents.len() {
+            return Err(Error::OutOfBounds);
+        }
+        self.contents[start..end].copy_from_slice(data);
+        Ok(())
+    }
+}
+
 #[derive(Eq, PartialEq, Clone, Debug)]
 pub struct DevicePath {
     pub path: Vec<String>
@@ -36,3 +290,94 @@ impl fmt::Display for DevicePath {
         write!(f, "{}", self.path.join("/"))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_scheduler_spawns_a_process_runner_that_echoes_packets() {
+        let (in_tx, in_rx) = sync::mpsc::channel();
+        let (out_tx, out_rx) = sync::mpsc::channel();
+
+        let runner = ProcessRunner {
+            process: Process { input: in_rx, output: out_tx },
+            transform: |pkt: Packet| pkt
+        };
+
+        let mut scheduler = Scheduler::new();
+        scheduler.register(Address(1), Box::new(runner), in_tx.clone());
+        scheduler.spawn_all();
+
+        in_tx.send(Packet::Chars(['h', 'i', '\0', '\0', '\0', '\0', '\0', '\0'])).unwrap();
+        match out_rx.recv().unwrap() {
+            Packet::Chars(chars) => assert_eq!(chars, ['h', 'i', '\0', '\0', '\0', '\0', '\0', '\0']),
+            _ => panic!("expected the runner to echo back Chars")
+        }
+
+        scheduler.shutdown();
+        assert!(out_rx.recv().is_err(), "runner thread should have exited after shutdown");
+    }
+
+    #[test]
+    fn test_scheduler_shutdown_drains_idle_runners() {
+        let (in_tx, in_rx) = sync::mpsc::channel();
+        let (out_tx, _out_rx) = sync::mpsc::channel();
+
+        let runner = ProcessRunner {
+            process: Process { input: in_rx, output: out_tx },
+            transform: |pkt: Packet| pkt
+        };
+
+        let mut scheduler = Scheduler::new();
+        scheduler.register(Address(1), Box::new(runner), in_tx);
+        scheduler.spawn_all();
+
+        // Nothing was ever sent; shutdown still has to wake the runner
+        // blocked on `recv` and wait for it to actually exit.
+        scheduler.shutdown();
+        assert!(scheduler.handles.is_empty());
+    }
+
+    #[test]
+    fn test_load_reads_whole_file_into_a_block() {
+        let path = std::env::temp_dir().join(format!("queus_test_load_{}_{}", std::process::id(), 1));
+        fs::write(&path, [1u8, 2, 3, 4, 5]).unwrap();
+
+        let mut block = MemoryBlock::load(path.to_str().unwrap()).unwrap();
+        assert_eq!(block.size(), 5);
+
+        let mut buf = [0u8; 5];
+        block.read(0, &mut buf).unwrap();
+        assert_eq!(buf, [1, 2, 3, 4, 5]);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_load_at_patches_an_existing_block_at_an_offset() {
+        let path = std::env::temp_dir().join(format!("queus_test_load_at_{}_{}", std::process::id(), 2));
+        fs::write(&path, [9u8, 9, 9]).unwrap();
+
+        let mut block = MemoryBlock::new(8);
+        block.load_at(2, path.to_str().unwrap()).unwrap();
+        assert_eq!(block.size(), 8);
+
+        let mut buf = [0u8; 3];
+        block.read(2, &mut buf).unwrap();
+        assert_eq!(buf, [9, 9, 9]);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_lock_rejects_writes() {
+        let mut block = MemoryBlock::new(4);
+        assert_eq!(block.read_only(), false);
+
+        block.lock();
+
+        assert_eq!(block.read_only(), true);
+        assert!(matches!(block.write(0, &[1]), Err(Error::ReadOnly)));
+    }
+}
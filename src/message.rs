@@ -2,7 +2,7 @@ use super::device::DevicePath;
 use super::Packet;
 
 pub struct DeviceMessage {
-    to: DevicePath,
-    from: DevicePath,
-    contents: Vec<Packet>
+    pub(crate) to: DevicePath,
+    pub(crate) from: DevicePath,
+    pub(crate) contents: Vec<Packet>
 }
\ No newline at end of file
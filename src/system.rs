@@ -1,7 +1,12 @@
-use std::collections::HashMap;
-use std::hash::Hash;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::mpsc;
 
-use crate::device::DevicePath;
+use crate::console::Packet;
+use crate::crypto::Encrypted;
+use crate::device::{AddressSpace, DevicePath, Error, MemoryBlock, Process, ProcessRunner, Scheduler};
+use crate::message::DeviceMessage;
 
 
 struct System {
@@ -10,12 +15,20 @@ struct System {
     network: Network<Device>,
 }
 
+///
+/// The running `Scheduler` returned by `System::spawn_scheduler`, along with
+/// the `Sender`/`Receiver` halves of each device's channels, keyed by
+/// address.
+///
+type SchedulerChannels = (Scheduler, HashMap<Address, mpsc::Sender<Packet>>, HashMap<Address, mpsc::Receiver<Packet>>);
+
 impl System {
     fn new() -> System {
         let mut network: Network<Device> = Network::new();
         let mainframe = Mainframe {
             id: "main".into(),
-            address: Address(1)
+            address: Address(1),
+            memory: None
         };
         let main_address = mainframe.address;
         network.connect_with_address(Device::Mainframe(mainframe), main_address, None);
@@ -39,10 +52,72 @@ impl System {
             network: network
         }
     }
+
+    ///
+    /// Spawns a `Scheduler` with one `Process`-backed runner each for the
+    /// mainframe (`Address(1)`) and the terminal (`Address(2)`); `net3` gets
+    /// no runner. Each has its own packet-handling behavior: the
+    /// mainframe's runner applies `MemoryRead`/`MemoryWrite` packets to its
+    /// own `MemoryBlock` via `AddressSpace` and answers with `MemoryData`;
+    /// the terminal's runner frames every packet it forwards through a
+    /// `Link`, encrypting on the way out and decrypting on the way back, the
+    /// way a packet crossing `net3` actually would. Returns the running
+    /// `Scheduler` along with the `Sender`/`Receiver` halves of each
+    /// device's channels, keyed by address, so callers can push packets in
+    /// and read back whatever the device's runner produced.
+    ///
+    fn spawn_scheduler(key: [u8; 32]) -> SchedulerChannels {
+        let mut scheduler = Scheduler::new();
+        let mut inputs = HashMap::new();
+        let mut outputs = HashMap::new();
+
+        let (main_in_tx, main_in_rx) = mpsc::channel();
+        let (main_out_tx, main_out_rx) = mpsc::channel();
+        let mut mainframe_memory = MemoryBlock::new(16);
+        let mainframe_runner = ProcessRunner {
+            process: Process { input: main_in_rx, output: main_out_tx },
+            transform: move |pkt: Packet| match pkt {
+                Packet::MemoryRead { addr, len } if len as usize <= 8 => {
+                    let mut data = [0u8; 8];
+                    match mainframe_memory.read(addr, &mut data[..len as usize]) {
+                        Ok(()) => Packet::MemoryData { addr, data },
+                        Err(_) => Packet::End
+                    }
+                },
+                Packet::MemoryWrite { addr, data } => {
+                    match mainframe_memory.write(addr, &data) {
+                        Ok(()) => Packet::MemoryData { addr, data },
+                        Err(_) => Packet::End
+                    }
+                },
+                _ => Packet::End
+            }
+        };
+        scheduler.register(Address(1), Box::new(mainframe_runner), main_in_tx.clone());
+        inputs.insert(Address(1), main_in_tx);
+        outputs.insert(Address(1), main_out_rx);
+
+        let (term_in_tx, term_in_rx) = mpsc::channel();
+        let (term_out_tx, term_out_rx) = mpsc::channel();
+        let link = NetworkNode { id: "net3".into(), address: Address(3) };
+        let terminal_runner = ProcessRunner {
+            process: Process { input: term_in_rx, output: term_out_tx },
+            transform: move |pkt: Packet| {
+                let encrypted = link.encrypt(&pkt, &key);
+                link.decrypt(&encrypted, &key).unwrap_or(Packet::End)
+            }
+        };
+        scheduler.register(Address(2), Box::new(terminal_runner), term_in_tx.clone());
+        inputs.insert(Address(2), term_in_tx);
+        outputs.insert(Address(2), term_out_rx);
+
+        scheduler.spawn_all();
+        (scheduler, inputs, outputs)
+    }
 }
 
 #[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
-pub struct Address(u16);
+pub struct Address(pub(crate) u16);
 
 struct Counter {
     value: u16
@@ -79,8 +154,12 @@ pub struct Network<N> {
     connections: HashMap<Address, Vec<Address>>,
 
     /// For any node N, this gets any node that is its parent (if it has one)
-    parent_connection: HashMap<Address, Address>
-    
+    parent_connection: HashMap<Address, Address>,
+
+    /// Typed metadata describing what a registered node is and what it costs
+    /// to cross, keyed by `Address`
+    registry: HashMap<Address, DeviceData>
+
 }
 
 impl<N: Eq + Hash> Network<N> {
@@ -91,10 +170,48 @@ impl<N: Eq + Hash> Network<N> {
             root: None,
             nodes: HashMap::new(),
             connections: HashMap::new(),
-            parent_connection: HashMap::new()
+            parent_connection: HashMap::new(),
+            registry: HashMap::new()
         }
     }
 
+    ///
+    /// Attaches `data` to `address` in the device registry, overwriting
+    /// whatever was registered there before.
+    ///
+    fn register(&mut self, address: Address, data: DeviceData) {
+        self.registry.insert(address, data);
+    }
+
+    ///
+    /// Looks up the `DeviceData` registered for `address`, if any.
+    ///
+    fn inspect(&self, address: Address) -> Option<&DeviceData> {
+        self.registry.get(&address)
+    }
+
+    ///
+    /// Scans the registry for every device of the given `kind`, in the spirit
+    /// of a `PrefixIterator` walk over a subset of known entries.
+    ///
+    fn devices_of_kind(&self, kind: DeviceKind) -> KindIterator<'_> {
+        KindIterator {
+            inner: self.registry.iter(),
+            kind
+        }
+    }
+
+    ///
+    /// Sums the incoming/outgoing fees registered for every address along
+    /// `route`, e.g. the hop-by-hop path a `DeviceMessage` takes.
+    ///
+    fn route_cost(&self, route: &[Address]) -> u32 {
+        route.iter()
+            .filter_map(|address| self.inspect(*address))
+            .map(|data| data.incoming_fee + data.outgoing_fee)
+            .sum()
+    }
+
     ///
     /// This will add a `new_node`, `N` into the network. A parent address may be supplied,
     /// and if it is the `new_node` will be a child of the `parent`. Additionally the 
@@ -147,6 +264,10 @@ impl<N: Eq + Hash> Network<N> {
         self.nodes.get(&address)
     }
 
+    fn get_node_mut(&mut self, address: Address) -> Option<&mut N> {
+        self.nodes.get_mut(&address)
+    }
+
     fn get_parent(&self, address: Address) -> Option<&Address> {
         self.parent_connection.get(&address)
     }
@@ -154,6 +275,223 @@ impl<N: Eq + Hash> Network<N> {
     fn get_children(&self, address: Address) -> Option<&Vec<Address>> {
         self.connections.get(&address)
     }
+
+    ///
+    /// Removes `address` from the network, unlinking it from its parent's
+    /// `connections`. When `reattach_children` is true, `address`'s children
+    /// are re-parented onto its former parent (or, if `address` was `root`,
+    /// the first child is promoted to `root` and the rest re-parented onto
+    /// it); otherwise the whole subtree rooted at `address` is removed.
+    /// Returns the removed node, if `address` was known.
+    ///
+    fn disconnect(&mut self, address: Address, reattach_children: bool) -> Option<N> {
+        let node = self.nodes.remove(&address)?;
+
+        let parent = self.parent_connection.remove(&address);
+        if let Some(p) = parent {
+            if let Some(siblings) = self.connections.get_mut(&p) {
+                siblings.retain(|child| *child != address);
+            }
+        }
+
+        let children = self.connections.remove(&address).unwrap_or_default();
+
+        if reattach_children {
+            self.reattach(children, parent);
+        } else {
+            for child in children {
+                self.remove_subtree(child);
+            }
+            if parent.is_none() {
+                self.root = None;
+            }
+        }
+
+        self.housekeep();
+        Some(node)
+    }
+
+    ///
+    /// Re-parents `children` onto `former_parent`. If there was no former
+    /// parent (the disconnected node was `root`), the first child is
+    /// promoted to `root` and the rest re-parented onto it instead; with no
+    /// children either, the network is left rootless.
+    ///
+    fn reattach(&mut self, children: Vec<Address>, former_parent: Option<Address>) {
+        match former_parent {
+            Some(parent) => {
+                for child in &children {
+                    self.parent_connection.insert(*child, parent);
+                }
+                self.connections.entry(parent).or_default().extend(children);
+            },
+            None => {
+                let mut remaining = children.into_iter();
+                match remaining.next() {
+                    Some(new_root) => {
+                        self.parent_connection.remove(&new_root);
+                        let siblings: Vec<Address> = remaining.collect();
+                        for child in &siblings {
+                            self.parent_connection.insert(*child, new_root);
+                        }
+                        self.connections.entry(new_root).or_default().extend(siblings);
+                        self.root = Some(new_root);
+                    },
+                    None => {
+                        self.root = None;
+                    }
+                }
+            }
+        }
+    }
+
+    ///
+    /// Recursively drops `address` and everything below it from `nodes`,
+    /// `connections`, and `parent_connection`.
+    ///
+    fn remove_subtree(&mut self, address: Address) {
+        let children = self.connections.remove(&address).unwrap_or_default();
+        self.nodes.remove(&address);
+        self.parent_connection.remove(&address);
+        for child in children {
+            self.remove_subtree(child);
+        }
+    }
+
+    ///
+    /// Sweeps `connections`, `parent_connection`, and `registry` for any
+    /// address that no longer has a backing node in `nodes`, dropping it so
+    /// the network stays consistent after a `disconnect`.
+    ///
+    fn housekeep(&mut self) {
+        let valid: HashSet<Address> = self.nodes.keys().copied().collect();
+        self.connections.retain(|address, children| {
+            children.retain(|child| valid.contains(child));
+            valid.contains(address)
+        });
+        self.parent_connection.retain(|address, parent| valid.contains(address) && valid.contains(parent));
+        self.registry.retain(|address, _| valid.contains(address));
+    }
+}
+
+impl Network<Device> {
+
+    ///
+    /// Resolves a `DevicePath` to the `Address` of the device it names, by
+    /// walking the tree from `root` and matching each path segment against
+    /// `Device::id` via `get_children`. Returns `None` if any segment fails
+    /// to match a child.
+    ///
+    fn resolve(&self, path: &DevicePath) -> Option<Address> {
+        let mut segments = path.path.iter();
+
+        let mut current = self.root?;
+        match (segments.next(), self.get_node(current)) {
+            (Some(first), Some(node)) if node.id() == *first => {},
+            _ => return None
+        }
+
+        for segment in segments {
+            let children = self.get_children(current)?;
+            current = *children.iter().find(|child| {
+                self.get_node(**child).map(|n| n.id()) == Some(segment.clone())
+            })?;
+        }
+
+        Some(current)
+    }
+
+    ///
+    /// The root-to-node list of addresses for `address`, found by following
+    /// `parent_connection` up to `root` and reversing the result.
+    ///
+    fn root_path(&self, address: Address) -> Vec<Address> {
+        let mut path = vec![address];
+        let mut current = address;
+        while let Some(parent) = self.get_parent(current) {
+            path.push(*parent);
+            current = *parent;
+        }
+        path.reverse();
+        path
+    }
+
+    ///
+    /// Computes the hop-by-hop route between `from` and `to`: the ascending
+    /// addresses from `from` up to their lowest common ancestor, followed by
+    /// the descending addresses from the ancestor down to `to`, with the
+    /// ancestor listed once.
+    ///
+    fn route(&self, from: Address, to: Address) -> Option<Vec<Address>> {
+        let from_path = self.root_path(from);
+        let to_path = self.root_path(to);
+
+        let mut common = 0;
+        while common < from_path.len() && common < to_path.len() && from_path[common] == to_path[common] {
+            common += 1;
+        }
+        if common == 0 {
+            return None;
+        }
+        let lca = common - 1;
+
+        let mut route: Vec<Address> = from_path[lca..].iter().rev().copied().collect();
+        route.extend(to_path[lca + 1..].iter().copied());
+        Some(route)
+    }
+
+    ///
+    /// Resolves both endpoints of `msg` and returns the ordered list of
+    /// devices its contents must traverse to get from `from` to `to`.
+    ///
+    fn deliver(&self, msg: DeviceMessage) -> Option<Vec<&Device>> {
+        let from = self.resolve(&msg.from)?;
+        let to = self.resolve(&msg.to)?;
+        let route = self.route(from, to)?;
+        route.into_iter().map(|address| self.get_node(address)).collect()
+    }
+
+    ///
+    /// Resolves `msg`'s route and sums the registered fees along it.
+    ///
+    fn message_cost(&self, msg: &DeviceMessage) -> Option<u32> {
+        let from = self.resolve(&msg.from)?;
+        let to = self.resolve(&msg.to)?;
+        let route = self.route(from, to)?;
+        Some(self.route_cost(&route))
+    }
+
+    ///
+    /// Routes `msg` the same way `deliver` does, then applies its lone
+    /// `Packet`'s memory request directly to the `Mainframe` it lands on --
+    /// the read/write half of the path a terminal uses to touch mainframe
+    /// memory. Returns `None` if the route doesn't resolve, doesn't land on
+    /// a `Mainframe`, the packet isn't a memory request, or (for a
+    /// `MemoryRead`) `len` is more than the 8 bytes `MemoryData` can carry.
+    ///
+    fn access_memory(&mut self, msg: DeviceMessage) -> Option<Packet> {
+        let from = self.resolve(&msg.from)?;
+        let to = self.resolve(&msg.to)?;
+        self.route(from, to)?;
+
+        match self.get_node_mut(to) {
+            Some(Device::Mainframe(mainframe)) => {
+                match msg.contents.into_iter().next()? {
+                    Packet::MemoryRead { addr, len } if len as usize <= 8 => {
+                        let mut data = [0u8; 8];
+                        mainframe.read(addr, &mut data[..len as usize]).ok()?;
+                        Some(Packet::MemoryData { addr, data })
+                    },
+                    Packet::MemoryWrite { addr, data } => {
+                        mainframe.write(addr, &data).ok()?;
+                        Some(Packet::MemoryData { addr, data })
+                    },
+                    _ => None
+                }
+            },
+            _ => None
+        }
+    }
 }
 
 // trait Networked {
@@ -166,10 +504,76 @@ struct NetworkNode {
     address: Address,
 }
 
-#[derive(Hash, PartialEq, Eq, Debug)]
+///
+/// A hop a `DeviceMessage` can cross that isn't necessarily trusted, able to
+/// encrypt a `Packet` to bytes and decrypt it back. `Process` channels carry
+/// plain `Packet`s; the terminal runner in `System::spawn_scheduler`
+/// encrypts and then decrypts each packet through a `Link` before sending it.
+///
+trait Link {
+    fn encrypt(&self, pkt: &Packet, key: &[u8; 32]) -> Vec<u8>;
+    fn decrypt(&self, data: &[u8], key: &[u8; 32]) -> Result<Packet, Error>;
+}
+
+impl Link for NetworkNode {
+    fn encrypt(&self, pkt: &Packet, key: &[u8; 32]) -> Vec<u8> {
+        Encrypted::encrypt(pkt, key)
+    }
+
+    fn decrypt(&self, data: &[u8], key: &[u8; 32]) -> Result<Packet, Error> {
+        Encrypted::decrypt(data, key)
+    }
+}
+
 struct Mainframe {
     id: String,
     address: Address,
+    memory: Option<MemoryBlock>,
+}
+
+impl PartialEq for Mainframe {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.address == other.address
+    }
+}
+
+impl Eq for Mainframe {}
+
+impl Hash for Mainframe {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+        self.address.hash(state);
+    }
+}
+
+impl fmt::Debug for Mainframe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mainframe")
+            .field("id", &self.id)
+            .field("address", &self.address)
+            .field("has_memory", &self.memory.is_some())
+            .finish()
+    }
+}
+
+impl AddressSpace for Mainframe {
+    fn size(&self) -> usize {
+        self.memory.as_ref().map(|mem| mem.size()).unwrap_or(0)
+    }
+
+    fn read(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Error> {
+        match &mut self.memory {
+            Some(mem) => mem.read(addr, data),
+            None => Err(Error::OutOfBounds)
+        }
+    }
+
+    fn write(&mut self, addr: u32, data: &[u8]) -> Result<(), Error> {
+        match &mut self.memory {
+            Some(mem) => mem.write(addr, data),
+            None => Err(Error::OutOfBounds)
+        }
+    }
 }
 
 #[derive(Hash, PartialEq, Eq, Debug)]
@@ -186,6 +590,57 @@ trait Addressable {
     fn address(&self) -> Address;
 }
 
+///
+/// The kind of device a registry entry describes.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum DeviceKind {
+    Mainframe,
+    NetworkNode,
+    Terminal,
+    Undefined
+}
+
+///
+/// Typed metadata attached to a registered device: what it is, a
+/// human-readable endpoint label, how many hops it adds before a message is
+/// considered final (`finality_delay`) or how many hops of propagation
+/// `latency` it introduces, and the per-direction packet fees charged to
+/// cross it.
+///
+#[derive(Debug, Clone, PartialEq)]
+struct DeviceData {
+    kind: DeviceKind,
+    endpoint: String,
+    finality_delay: Option<u32>,
+    latency: Option<u32>,
+    incoming_fee: u32,
+    outgoing_fee: u32
+}
+
+///
+/// Walks a `Network`'s registry for every entry matching a given
+/// `DeviceKind`, mirroring a `PrefixIterator`-style scan over a subset of
+/// known entries.
+///
+struct KindIterator<'a> {
+    inner: std::collections::hash_map::Iter<'a, Address, DeviceData>,
+    kind: DeviceKind
+}
+
+impl<'a> Iterator for KindIterator<'a> {
+    type Item = (Address, &'a DeviceData);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (address, data) in &mut self.inner {
+            if data.kind == self.kind {
+                return Some((*address, data));
+            }
+        }
+        None
+    }
+}
+
 #[derive(Hash, PartialEq, Eq, Debug)]
 enum Device {
     Mainframe(Mainframe),
@@ -294,6 +749,63 @@ mod test {
         assert_eq!(network.get_parent(child), Some(&root));
     }
 
+    #[test]
+    fn test_disconnect_leaf() {
+        let mut network: Network<TestNode> = Network::new();
+        let root = network.connect_to_parent(TestNode("Hello".into()), None);
+        let child = network.connect_to_parent(TestNode("World".into()), Some(root));
+
+        let removed = network.disconnect(child, false);
+
+        assert_eq!(removed, Some(TestNode("World".into())));
+        assert_eq!(network.get_node(child), None);
+        assert_eq!(network.get_children(root), Some(&Vec::new()));
+    }
+
+    #[test]
+    fn test_disconnect_reattaches_children_to_former_parent() {
+        let mut network: Network<TestNode> = Network::new();
+        let root = network.connect_to_parent(TestNode("Hello".into()), None);
+        let middle = network.connect_to_parent(TestNode("World".into()), Some(root));
+        let grandchild = network.connect_to_parent(TestNode("Middle".into()), Some(middle));
+
+        let removed = network.disconnect(middle, true);
+
+        assert_eq!(removed, Some(TestNode("World".into())));
+        assert_eq!(network.get_parent(grandchild), Some(&root));
+        assert_eq!(network.get_children(root), Some(&vec![grandchild]));
+        assert_eq!(network.get_node(middle), None);
+    }
+
+    #[test]
+    fn test_disconnect_without_reattach_removes_whole_subtree() {
+        let mut network: Network<TestNode> = Network::new();
+        let root = network.connect_to_parent(TestNode("Hello".into()), None);
+        let middle = network.connect_to_parent(TestNode("World".into()), Some(root));
+        let grandchild = network.connect_to_parent(TestNode("Middle".into()), Some(middle));
+
+        network.disconnect(middle, false);
+
+        assert_eq!(network.get_node(middle), None);
+        assert_eq!(network.get_node(grandchild), None);
+        assert_eq!(network.get_children(root), Some(&Vec::new()));
+    }
+
+    #[test]
+    fn test_disconnect_root_promotes_first_child() {
+        let mut network: Network<TestNode> = Network::new();
+        let root = network.connect_to_parent(TestNode("Hello".into()), None);
+        let first = network.connect_to_parent(TestNode("World".into()), Some(root));
+        let second = network.connect_to_parent(TestNode("Middle".into()), Some(root));
+
+        network.disconnect(root, true);
+
+        assert_eq!(network.root, Some(first));
+        assert_eq!(network.get_parent(first), None);
+        assert_eq!(network.get_parent(second), Some(&first));
+        assert_eq!(network.get_children(first), Some(&vec![second]));
+    }
+
     #[test]
     fn test_system_structure() {
         let system = System::new();
@@ -318,6 +830,250 @@ mod test {
         let mainframe = system.network.get_node(system.mainframe).unwrap();
         assert_eq!(mainframe.device_path(&system.network), "/main".into());
     }
+
+    #[test]
+    fn test_resolve() {
+        let system = System::new();
+
+        assert_eq!(system.network.resolve(&"/main".into()), Some(system.mainframe));
+        assert_eq!(system.network.resolve(&"/main/net3/term1".into()), Some(system.terminal));
+        assert_eq!(system.network.resolve(&"/main/nope".into()), None);
+    }
+
+    #[test]
+    fn test_route() {
+        let system = System::new();
+
+        // terminal sits two hops below the mainframe (root), via net3
+        let route = system.network.route(system.terminal, system.mainframe).unwrap();
+        let net3 = *system.network.get_children(system.mainframe).unwrap().iter()
+            .find(|a| system.network.get_node(**a).unwrap().id() == "net3")
+            .unwrap();
+
+        assert_eq!(route, vec![system.terminal, net3, system.mainframe]);
+        assert_eq!(system.network.route(system.mainframe, system.mainframe), Some(vec![system.mainframe]));
+    }
+
+    #[test]
+    fn test_deliver() {
+        let system = System::new();
+
+        let msg = DeviceMessage {
+            to: "/main".into(),
+            from: "/main/net3/term1".into(),
+            contents: Vec::new()
+        };
+
+        let ids: Vec<String> = system.network.deliver(msg).unwrap()
+            .iter().map(|d| d.id()).collect();
+
+        assert_eq!(ids, vec!["term1".to_string(), "net3".to_string(), "main".to_string()]);
+    }
+
+    #[test]
+    fn test_disconnect_prunes_registry_entries() {
+        let mut system = System::new();
+
+        system.network.register(system.terminal, DeviceData {
+            kind: DeviceKind::Terminal,
+            endpoint: "term1".into(),
+            finality_delay: None,
+            latency: None,
+            incoming_fee: 1,
+            outgoing_fee: 1
+        });
+
+        system.network.disconnect(system.terminal, false);
+
+        assert_eq!(system.network.inspect(system.terminal), None);
+    }
+
+    #[test]
+    fn test_register_and_inspect() {
+        let mut system = System::new();
+
+        system.network.register(system.mainframe, DeviceData {
+            kind: DeviceKind::Mainframe,
+            endpoint: "main".into(),
+            finality_delay: None,
+            latency: None,
+            incoming_fee: 2,
+            outgoing_fee: 3
+        });
+
+        let data = system.network.inspect(system.mainframe).unwrap();
+        assert_eq!(data.kind, DeviceKind::Mainframe);
+        assert_eq!(data.endpoint, "main");
+        assert_eq!(system.network.inspect(system.terminal), None);
+    }
+
+    #[test]
+    fn test_devices_of_kind() {
+        let mut system = System::new();
+
+        system.network.register(system.mainframe, DeviceData {
+            kind: DeviceKind::Mainframe,
+            endpoint: "main".into(),
+            finality_delay: None,
+            latency: None,
+            incoming_fee: 0,
+            outgoing_fee: 0
+        });
+        system.network.register(system.terminal, DeviceData {
+            kind: DeviceKind::Terminal,
+            endpoint: "term1".into(),
+            finality_delay: None,
+            latency: None,
+            incoming_fee: 0,
+            outgoing_fee: 0
+        });
+
+        let mainframes: Vec<Address> = system.network.devices_of_kind(DeviceKind::Mainframe)
+            .map(|(address, _)| address).collect();
+
+        assert_eq!(mainframes, vec![system.mainframe]);
+    }
+
+    #[test]
+    fn test_message_cost_sums_fees_along_route() {
+        let mut system = System::new();
+
+        let net3 = *system.network.get_parent(system.terminal).unwrap();
+
+        for (address, fee) in [(system.terminal, 1), (net3, 2), (system.mainframe, 4)] {
+            system.network.register(address, DeviceData {
+                kind: DeviceKind::Undefined,
+                endpoint: "".into(),
+                finality_delay: None,
+                latency: None,
+                incoming_fee: fee,
+                outgoing_fee: fee
+            });
+        }
+
+        let msg = DeviceMessage {
+            to: "/main".into(),
+            from: "/main/net3/term1".into(),
+            contents: Vec::new()
+        };
+
+        // (1 + 1) + (2 + 2) + (4 + 4)
+        assert_eq!(system.network.message_cost(&msg), Some(14));
+    }
+
+    #[test]
+    fn test_terminal_can_read_write_mainframe_memory_over_the_route() {
+        let mut system = System::new();
+
+        if let Some(Device::Mainframe(mainframe)) = system.network.get_node_mut(system.mainframe) {
+            mainframe.memory = Some(MemoryBlock::new(16));
+        }
+
+        let write = DeviceMessage {
+            to: "/main".into(),
+            from: "/main/net3/term1".into(),
+            contents: vec![Packet::MemoryWrite { addr: 2, data: [1, 2, 3, 4, 5, 6, 7, 8] }]
+        };
+        let response = system.network.access_memory(write).unwrap();
+        assert!(matches!(response, Packet::MemoryData { addr: 2, data: [1, 2, 3, 4, 5, 6, 7, 8] }));
+
+        let read = DeviceMessage {
+            to: "/main".into(),
+            from: "/main/net3/term1".into(),
+            contents: vec![Packet::MemoryRead { addr: 2, len: 3 }]
+        };
+        let response = system.network.access_memory(read).unwrap();
+        assert!(matches!(response, Packet::MemoryData { addr: 2, data: [1, 2, 3, 0, 0, 0, 0, 0] }));
+    }
+
+    #[test]
+    fn test_access_memory_rejects_a_route_that_does_not_land_on_a_mainframe() {
+        let mut system = System::new();
+
+        let msg = DeviceMessage {
+            to: "/main/net3/term1".into(),
+            from: "/main".into(),
+            contents: vec![Packet::MemoryRead { addr: 0, len: 1 }]
+        };
+        assert!(system.network.access_memory(msg).is_none());
+    }
+
+    #[test]
+    fn test_access_memory_rejects_a_read_longer_than_memory_data_can_carry() {
+        let mut system = System::new();
+
+        if let Some(Device::Mainframe(mainframe)) = system.network.get_node_mut(system.mainframe) {
+            mainframe.memory = Some(MemoryBlock::new(16));
+        }
+
+        let msg = DeviceMessage {
+            to: "/main".into(),
+            from: "/main/net3/term1".into(),
+            contents: vec![Packet::MemoryRead { addr: 0, len: 9 }]
+        };
+        assert!(system.network.access_memory(msg).is_none());
+    }
+
+    #[test]
+    fn test_spawn_scheduler_mainframe_runner_handles_memory_packets() {
+        let (mut scheduler, inputs, outputs) = System::spawn_scheduler([3u8; 32]);
+
+        let main_in = &inputs[&Address(1)];
+        let main_out = &outputs[&Address(1)];
+
+        main_in.send(Packet::MemoryWrite { addr: 0, data: [1, 2, 3, 4, 5, 6, 7, 8] }).unwrap();
+        assert!(matches!(main_out.recv().unwrap(), Packet::MemoryData { addr: 0, data: [1, 2, 3, 4, 5, 6, 7, 8] }));
+
+        main_in.send(Packet::MemoryRead { addr: 0, len: 4 }).unwrap();
+        assert!(matches!(main_out.recv().unwrap(), Packet::MemoryData { addr: 0, data: [1, 2, 3, 4, 0, 0, 0, 0] }));
+
+        scheduler.shutdown();
+    }
+
+    #[test]
+    fn test_spawn_scheduler_terminal_runner_round_trips_packets_through_its_link() {
+        let (mut scheduler, inputs, outputs) = System::spawn_scheduler([5u8; 32]);
+
+        let term_in = &inputs[&Address(2)];
+        let term_out = &outputs[&Address(2)];
+
+        let chars = ['h', 'i', '\0', '\0', '\0', '\0', '\0', '\0'];
+        term_in.send(Packet::Chars(chars)).unwrap();
+        match term_out.recv().unwrap() {
+            Packet::Chars(got) => assert_eq!(got, chars),
+            _ => panic!("expected the terminal runner's Link round trip to preserve Chars")
+        }
+
+        scheduler.shutdown();
+    }
+
+    #[test]
+    fn test_mainframe_memory() {
+        let mut mainframe = Mainframe {
+            id: "main".into(),
+            address: Address(1),
+            memory: Some(MemoryBlock::new(8))
+        };
+
+        assert_eq!(mainframe.size(), 8);
+
+        mainframe.write(2, &[1, 2, 3]).unwrap();
+        let mut buf = [0u8; 3];
+        mainframe.read(2, &mut buf).unwrap();
+        assert_eq!(buf, [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_mainframe_without_memory_errors() {
+        let mut mainframe = Mainframe {
+            id: "main".into(),
+            address: Address(1),
+            memory: None
+        };
+
+        assert_eq!(mainframe.size(), 0);
+        assert!(mainframe.write(0, &[1]).is_err());
+    }
 }
 
 